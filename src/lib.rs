@@ -1,40 +1,163 @@
 use std::ptr;
 use std::mem;
+use std::sync::Arc;
+use std::iter::FromIterator;
 
 const max_values_per_leaf: usize = 4;
 
+// A leaf below this occupancy must borrow from or merge with a sibling.
+const min_values_per_leaf: usize = max_values_per_leaf / 2;
+
+// Roughly B - B^e: kept equal to the leaf capacity for this toy sizing, which
+// is what gives flushes their batching (a full buffer moves several messages
+// down the tree in one pass instead of one root-to-leaf walk per write).
+const max_buffer_size: usize = 4;
+
+// A branch with more pivots than this must split, propagating a new pivot
+// up to its parent (or growing the root, if it has none).
+const max_pivots_per_branch: usize = 4;
+
 /* A pivot is a key and a node of the subtree of values >= that key. */
+///
+/// `child` is reference-counted rather than uniquely owned so that a
+/// `Snapshot` can hold onto a subtree after the writer has moved past it;
+/// see `cow_node`.
 struct Pivot<K, V> {
     min_key: K,
-    child: Box<Node<K, V>>
+    child: Arc<Node<K, V>>
+}
+
+impl<K, V> Clone for Pivot<K, V> where K: Clone {
+    fn clone(&self) -> Self {
+        Pivot { min_key: self.min_key.clone(), child: self.child.clone() }
+    }
+}
+
+/// A pending write, buffered at a `Node::Branch` instead of being applied
+/// straight to a leaf.
+///
+/// Messages are resolved newest-first: a `Delete` must mask an `Insert` of
+/// the same key that is still sitting lower in the tree.
+enum Message<K, V> {
+    Insert(K, V),
+    Delete(K),
+}
+
+impl<K, V> Message<K, V> {
+    fn key(&self) -> &K {
+        match *self {
+            Message::Insert(ref k, _) => k,
+            Message::Delete(ref k) => k,
+        }
+    }
+}
+
+impl<K, V> Clone for Message<K, V> where K: Clone, V: Clone {
+    fn clone(&self) -> Self {
+        match *self {
+            Message::Insert(ref k, ref v) => Message::Insert(k.clone(), v.clone()),
+            Message::Delete(ref k) => Message::Delete(k.clone()),
+        }
+    }
+}
+
+/// What happened as a result of applying something to a node, reported back
+/// to the caller holding the pivot that points at it.
+enum NodeChange<K, V> {
+    /// Nothing structural changed.
+    None,
+    /// The node split in two; this is the new right sibling, to be inserted
+    /// as a pivot next to the (mutated, now smaller) original node.
+    Split(Pivot<K, V>),
+    /// The node dropped below minimum occupancy; the caller (which holds
+    /// its siblings) must borrow from one or merge it away.
+    Underflow,
 }
 
 struct LeafNode<K, V> {
-    elements: [(K, V); max_values_per_leaf],
+    // Only the prefix `elements[0..len]` is initialized; the rest is spare
+    // capacity a `slice_insert` can grow into without reading it first. See
+    // the `Drop` impl below, which must only drop that prefix.
+    elements: [mem::MaybeUninit<(K, V)>; max_values_per_leaf],
     // must be <= max_values_per_leaf
     len: usize,
+    // The write transaction that last touched this leaf; see `cow_node`.
+    txn: u64,
 }
 
-impl<K, V> LeafNode<K, V> where K: Copy, V: Clone {
-    fn empty() -> Self {
+impl<K, V> Drop for LeafNode<K, V> {
+    fn drop(&mut self) {
         unsafe {
-            Self { elements: mem::uninitialized(), len: 0 }
+            for item in &mut self.elements[0..self.len] {
+                ptr::drop_in_place(item.as_mut_ptr());
+            }
         }
     }
+}
+
+impl<K, V> LeafNode<K, V> where K: Clone, V: Clone {
+    fn empty(txn: u64) -> Self {
+        // Safe: an array of `MaybeUninit`s doesn't need its elements
+        // initialized, only the array itself, and `len: 0` below means
+        // `Drop` won't touch any of them until they really are.
+        let elements = unsafe {
+            mem::MaybeUninit::<[mem::MaybeUninit<(K, V)>; max_values_per_leaf]>::uninit().assume_init()
+        };
+        Self { elements, len: 0, txn }
+    }
 
-    fn from(items: &[(K, V)]) -> Self {
+    fn from(items: &[(K, V)], txn: u64) -> Self {
         debug_assert!(items.len() <= max_values_per_leaf);
-        let mut result = Self::empty();
-        result.elements.clone_from_slice(items);
+        let mut result = Self::empty(txn);
+        for (i, item) in items.iter().enumerate() {
+            result.elements[i] = mem::MaybeUninit::new(item.clone());
+        }
+        result.len = items.len();
         result
     }
 
     fn valid_elements_mut(&mut self) -> &mut [(K, V)] {
-        &mut self.elements[0..self.len]
+        unsafe { slice_assume_init_mut(&mut self.elements[0..self.len]) }
     }
 
     fn valid_elements(&self) -> &[(K, V)] {
-        &self.elements[0..self.len]
+        unsafe { slice_assume_init_ref(&self.elements[0..self.len]) }
+    }
+
+    // One slot past `valid_elements_mut`, for `slice_insert`ing into a leaf
+    // that's known to have room (`len < max_values_per_leaf`): the slot at
+    // `len` is uninitialized but backed by the array, and is exactly where
+    // the insert needs to shift into.
+    fn with_one_spare_mut(&mut self) -> &mut [mem::MaybeUninit<(K, V)>] {
+        debug_assert!(self.len < max_values_per_leaf);
+        &mut self.elements[0..self.len + 1]
+    }
+
+    /// Move the valid elements out into an owned `Vec`, leaving this leaf
+    /// empty (as if just `clear`ed). Used by splits and merges to hand
+    /// elements to a new leaf without cloning them, unlike `from`, which
+    /// clones because its callers (COW and bulk loading) only borrow the
+    /// items and must leave the originals intact.
+    fn take_valid_elements(&mut self) -> Vec<(K, V)> {
+        let taken = self.elements[0..self.len]
+            .iter()
+            .map(|item| unsafe { ptr::read(item.as_ptr()) })
+            .collect();
+        self.len = 0;
+        taken
+    }
+
+    /// Build a leaf by moving `items` in directly, rather than cloning them
+    /// the way `from` must.
+    fn from_vec(items: Vec<(K, V)>, txn: u64) -> Self {
+        debug_assert!(items.len() <= max_values_per_leaf);
+        let mut result = Self::empty(txn);
+        let len = items.len();
+        for (i, item) in items.into_iter().enumerate() {
+            result.elements[i] = mem::MaybeUninit::new(item);
+        }
+        result.len = len;
+        result
     }
 }
 
@@ -42,139 +165,401 @@ enum Node<K, V>
 {
     Branch {
         pivots: Vec<Pivot<K, V>>,
+        // Pending messages not yet routed to a child. Shadows the subtree:
+        // a lookup must check here before descending.
+        buffer: Vec<Message<K, V>>,
+        // The write transaction that last touched this branch; see `cow_node`.
+        txn: u64,
     },
     Leaf(LeafNode<K, V>)
 }
 
-impl<K, V> Node<K, V> where K: Copy + Ord, V: Clone {
+impl<K, V> Node<K, V> where K: Ord + Clone, V: Clone {
     fn min_key(&self) -> K {
         match *self {
-            Node::Branch { pivots: ref p } => {
-                p[0].min_key
+            Node::Branch { pivots: ref p, .. } => {
+                p[0].min_key.clone()
             },
             Node::Leaf(ref leaf) => {
                 debug_assert_ne!(leaf.len, 0);
-                leaf.elements[0].0
+                leaf.valid_elements()[0].0.clone()
             }
         }
     }
 
-    fn insert(&mut self, key: K, value: V) {
-        let replace_node: Option<Self> = match *self {
-            Node::Branch { ref mut pivots } => {
-                // Find a child node whose keys are not before the target key
-                match pivots.iter().position(|ref p| key <= p.min_key) {
-                    Some(i) => {
-                        // If there is one, insert into it and update the pivot key
-                        let pivot = &mut pivots[i];
-                        pivot.min_key = key;
-                        pivot.child.insert(key, value)
-                    },
-                    // o/w, insert a new leaf at the end
-                    None => pivots.push(Pivot {min_key: key, child: Box::new(Node::Leaf(LeafNode::empty()))})
-                };
-                None
-            }
+    fn txn(&self) -> u64 {
+        match *self {
+            Node::Branch { txn, .. } => txn,
+            Node::Leaf(ref leaf) => leaf.txn,
+        }
+    }
+
+    /// Whether this node holds no live data: a leaf with nothing in it, or a
+    /// branch whose only child is itself degenerate. This is the state a
+    /// node is left in when `fix_underflow` can't borrow from or merge with
+    /// a sibling because it's the only child its parent has; it recurses
+    /// because that can cascade (a branch's sole child emptying can leave
+    /// the branch itself with nothing worth keeping, and so on upward).
+    /// `min_key` can't be computed for an empty leaf, so callers must check
+    /// this before refreshing a pivot's `min_key` from such a child.
+    fn is_degenerate(&self) -> bool {
+        match *self {
+            Node::Leaf(ref leaf) => leaf.len == 0,
+            Node::Branch { ref pivots, .. } => pivots.len() == 1 && pivots[0].child.is_degenerate(),
+        }
+    }
+
+    /// A shallow copy of this node, tagged with `txn`: pivots keep pointing
+    /// at the same `Arc` children (a path copy-on-write leaves untouched
+    /// subtrees shared with any snapshot that still references them).
+    fn shallow_clone(&self, txn: u64) -> Self {
+        match *self {
+            Node::Branch { ref pivots, ref buffer, .. } => Node::Branch {
+                pivots: pivots.clone(),
+                buffer: buffer.clone(),
+                txn,
+            },
+            Node::Leaf(ref leaf) => Node::Leaf(LeafNode::from(leaf.valid_elements(), txn)),
+        }
+    }
+
+    /// Apply a message directly to this node, which must be a leaf.
+    ///
+    /// Branch nodes never apply messages themselves; they buffer them and
+    /// route them down via `push_message`/`flush`.
+    fn apply_message(&mut self, message: Message<K, V>, txn: u64) -> NodeChange<K, V> {
+        match message {
+            Message::Insert(key, value) => self.insert_leaf(key, value, txn),
+            Message::Delete(key) => self.delete_leaf(&key),
+        }
+    }
+
+    fn insert_leaf(&mut self, key: K, value: V, txn: u64) -> NodeChange<K, V> {
+        match *self {
+            Node::Branch { .. } => unreachable!("messages are routed to a leaf before being applied"),
             Node::Leaf(ref mut leaf) => {
-                let index = leaf.valid_elements_mut().binary_search_by_key(&key, |&(k, _)| k);
+                let index = leaf.valid_elements().binary_search_by(|elem| elem.0.cmp(&key));
                 match index {
+                    // key is present: replace the value but keep the stored
+                    // key. The slot at `i` is initialized, so assigning
+                    // through `valid_elements_mut` drops the old value.
+                    Ok(i) => {
+                        leaf.valid_elements_mut()[i].1 = value;
+                        NodeChange::None
+                    }
                     Err(i) => { // key is absent, true insert
                         if leaf.len < max_values_per_leaf {
                             // there's space left, just insert
                             unsafe {
-                                slice_insert(leaf.valid_elements_mut(), i, (key, value))
+                                slice_insert(leaf.with_one_spare_mut(), i, mem::MaybeUninit::new((key, value)))
                             }
                             leaf.len += 1;
-                            None
+                            NodeChange::None
                         } else {
-                            // must split the node: create the new node here
-                            let new_branch = {
-                                let (left, right) = leaf.valid_elements_mut().split_at(max_values_per_leaf / 2);
-                                let left_leaf = Box::new(Node::Leaf(LeafNode::from(left)));
-                                let right_leaf = Box::new(Node::Leaf(LeafNode::from(right)));
-                                Node::Branch {
-                                    pivots: vec![
-                                        Pivot { min_key: left_leaf.min_key(), child: left_leaf },
-                                        Pivot { min_key: right_leaf.min_key(), child: right_leaf }
-                                    ]
-                                }
-                            };
-                            Some(new_branch)
+                            // No room: split, including the new pair this time, and
+                            // hand the right half up to the caller as a new pivot.
+                            // Elements move into the two new leaves rather than
+                            // cloning, so no value is ever live in two places at once.
+                            let mut items = leaf.take_valid_elements();
+                            items.insert(i, (key, value));
+                            let right_items = items.split_off(items.len() / 2);
+                            *leaf = LeafNode::from_vec(items, txn);
+                            let right_leaf = Arc::new(Node::Leaf(LeafNode::from_vec(right_items, txn)));
+                            let min_key = right_leaf.min_key();
+                            NodeChange::Split(Pivot { min_key, child: right_leaf })
                         }
-                    },
-                    // key is present, replace
-                    Ok(i) => {
-                        leaf.elements[i] = (key, value);
-                        None
                     }
                 }
             }
-        };
-        if let Some(new_branch) = replace_node {
-            *self = new_branch
         }
     }
 
-    fn delete(&mut self, key: K) {
+    fn delete_leaf(&mut self, key: &K) -> NodeChange<K, V> {
         match *self {
-            Node::Branch { ref mut pivots } => {
-                // Find a child node whose keys are not before the target key
-                match pivots.iter_mut().find(|ref p| key <= p.min_key) {
-                    Some(ref mut pivot) => {
-                        // If there is one, delete from it and update the pivot key
-                        pivot.child.delete(key);
-                        pivot.min_key = pivot.child.min_key()
-                    },
-                    // o/w, nothing to do
-                    None => ()
-                }
-            }
+            Node::Branch { .. } => unreachable!("messages are routed to a leaf before being applied"),
             Node::Leaf(ref mut leaf) if leaf.len > 0 => {
-                let index = leaf.valid_elements_mut().binary_search_by_key(&key, |&(k, _)| k);
+                let index = leaf.valid_elements().binary_search_by(|elem| elem.0.cmp(key));
                 match index {
-                    Err(_) => (),
+                    Err(_) => NodeChange::None,
                     Ok(i) => {
                         unsafe {
                             slice_remove(leaf.valid_elements_mut(), i);
-                            leaf.len -= 1;
                         }
+                        leaf.len -= 1;
+                        if leaf.len < min_values_per_leaf {
+                            NodeChange::Underflow
+                        } else {
+                            NodeChange::None
+                        }
+                    }
+                }
+            }
+            Node::Leaf(_) => NodeChange::None
+        }
+    }
+
+    /// Enqueue a message at this node: buffered if it's a branch, applied
+    /// immediately if it's a leaf.
+    fn push_message(&mut self, message: Message<K, V>, txn: u64) -> NodeChange<K, V> {
+        match *self {
+            Node::Branch { ref mut buffer, .. } => buffer.push(message),
+            Node::Leaf(_) => return self.apply_message(message, txn),
+        }
+        self.flush_if_needed(txn)
+    }
+
+    fn flush_if_needed(&mut self, txn: u64) -> NodeChange<K, V> {
+        let needs_flush = match *self {
+            Node::Branch { ref buffer, .. } => buffer.len() > max_buffer_size,
+            Node::Leaf(_) => false,
+        };
+        if needs_flush {
+            self.flush(txn)
+        } else {
+            NodeChange::None
+        }
+    }
+
+    /// Drain this branch's buffer, routing each message to the pivot
+    /// interval it belongs to (applying it directly if that pivot's child
+    /// is a leaf), fixing up underflowed or split children as they're
+    /// reported, then splitting this branch itself if it now has too many
+    /// pivots.
+    fn flush(&mut self, txn: u64) -> NodeChange<K, V> {
+        let mut result = NodeChange::None;
+        if let Node::Branch { ref mut pivots, ref mut buffer, .. } = *self {
+            let messages = mem::replace(buffer, Vec::new());
+            for message in messages {
+                let i = find_pivot(pivots, message.key());
+                let child = cow_node(&mut pivots[i].child, txn);
+                match child.push_message(message, txn) {
+                    // A child left degenerate by fix_underflow's "nothing to
+                    // borrow from or merge with" case has no min_key to read.
+                    NodeChange::None => {
+                        if !pivots[i].child.is_degenerate() {
+                            pivots[i].min_key = pivots[i].child.min_key();
+                        }
+                    }
+                    NodeChange::Split(new_pivot) => {
+                        pivots[i].min_key = pivots[i].child.min_key();
+                        pivots.insert(i + 1, new_pivot);
                     }
+                    // the underflowed child may be degenerate, so don't touch
+                    // its min_key here; fix_underflow fixes up whichever
+                    // pivot keys actually changed.
+                    NodeChange::Underflow => fix_underflow(pivots, i, txn),
                 }
             }
-            _ => ()
+            if pivots.len() > max_pivots_per_branch {
+                let split_at = pivots.len() / 2;
+                let right_pivots = pivots.split_off(split_at);
+                let right_branch = Arc::new(Node::Branch { pivots: right_pivots, buffer: Vec::new(), txn });
+                let min_key = right_branch.min_key();
+                result = NodeChange::Split(Pivot { min_key, child: right_branch });
+            } else if pivots.len() == 1 && pivots[0].child.is_degenerate() {
+                // This branch's only child borrowed/merged down to nothing
+                // (or is itself a degenerate branch): this branch is now
+                // just as degenerate, and by the same rule. Rather than
+                // mutating ourselves into a `Leaf` in place — which would
+                // leave a `Leaf` sitting among `Branch` siblings in our
+                // parent's pivots, since children at one level are otherwise
+                // always uniformly typed — report our own underflow and let
+                // the parent drop our pivot outright, exactly as it would an
+                // emptied leaf's.
+                result = NodeChange::Underflow;
+            }
         }
+        result
     }
 
-    fn get(&self, key: K) -> Option<&V> {
+    fn get(&self, key: &K) -> Option<&V> {
         match *self {
-            Node::Branch { ref pivots } => {
-                // Find a child node whose keys are not before the target key
-                match pivots.iter().find(|ref p| key <= p.min_key) {
-                    Some(ref pivot) => {
-                        // If there is one, query it
-                        pivot.child.get(key)
-                    },
-                    // o/w, the key doesn't exist
-                    None => None
+            Node::Branch { ref pivots, ref buffer, .. } => {
+                // The buffer holds messages newer than anything below it, so
+                // a match here wins even if the leaf still holds a stale value.
+                match buffer.iter().rev().find(|m| m.key() == key) {
+                    Some(&Message::Insert(_, ref v)) => return Some(v),
+                    Some(&Message::Delete(_)) => return None,
+                    None => ()
                 }
+                pivots[find_pivot(pivots, key)].child.get(key)
             }
             Node::Leaf(ref leaf) if leaf.len > 0 => {
-                let index = leaf.valid_elements().binary_search_by_key(&key, |&(k, _)| k);
+                let index = leaf.valid_elements().binary_search_by(|elem| elem.0.cmp(key));
                 match index {
                     Err(_) => None,
-                    Ok(i) => Some(&leaf.elements[i].1)
+                    Ok(i) => Some(&leaf.valid_elements()[i].1)
                 }
             }
             _ => None
         }
     }
+
+    /// Append every live `(key, value)` reachable from this node, in no
+    /// particular order, resolving buffered messages against their subtree
+    /// as they're encountered. Used to build a `Snapshot`'s `iter()`; the
+    /// caller is responsible for sorting the result.
+    fn collect_into(&self, out: &mut Vec<(K, V)>) {
+        match *self {
+            Node::Branch { ref pivots, ref buffer, .. } => {
+                for pivot in pivots {
+                    pivot.child.collect_into(out);
+                }
+                // Buffered messages are newer than anything just collected
+                // from below, so they overwrite (or remove) those entries.
+                for message in buffer {
+                    match *message {
+                        Message::Insert(ref k, ref v) => {
+                            // As with `insert`, an existing key is kept and
+                            // only its value is replaced.
+                            match out.iter_mut().find(|e| e.0 == *k) {
+                                Some(entry) => entry.1 = v.clone(),
+                                None => out.push((k.clone(), v.clone())),
+                            }
+                        }
+                        Message::Delete(ref k) => {
+                            out.retain(|(ek, _)| ek != k);
+                        }
+                    }
+                }
+            }
+            Node::Leaf(ref leaf) => {
+                for item in leaf.valid_elements() {
+                    out.push(item.clone());
+                }
+            }
+        }
+    }
 }
 
+/// The pivot whose child owns `key`: the rightmost one whose `min_key` does
+/// not exceed it, since a pivot's child holds every key from `min_key` up to
+/// (but not including) the next pivot's. A `key` smaller than every pivot's
+/// `min_key` still belongs to the leftmost child; its bucket just grew a new
+/// lower bound.
+fn find_pivot<K, V>(pivots: &[Pivot<K, V>], key: &K) -> usize where K: Ord {
+    pivots.iter().rposition(|p| &p.min_key <= key).unwrap_or(0)
+}
+
+/// Get a uniquely-owned, mutable view of the node behind `arc`, copying it
+/// first if it's tagged with an older transaction than `txn` (meaning it
+/// may still be reachable from a live `Snapshot`). A node already tagged
+/// with `txn` was created or copied since the last snapshot, so no snapshot
+/// can reach it and it is unshared; no further copy is needed: that's the
+/// COW amortization.
+fn cow_node<K, V>(arc: &mut Arc<Node<K, V>>, txn: u64) -> &mut Node<K, V>
+    where K: Ord + Clone, V: Clone
+{
+    if arc.txn() != txn {
+        let cloned = arc.shallow_clone(txn);
+        *arc = Arc::new(cloned);
+    }
+    Arc::get_mut(arc).expect("node just tagged with the current txn must be uniquely owned")
+}
+
+/// Fix an underflowed child at `pivots[index]` by borrowing a single
+/// element from a sibling that can spare one, or merging with a sibling
+/// that can't (removing whichever pivot the merge absorbs).
+///
+/// A degenerate child (an emptied leaf, or a branch that collapsed the same
+/// way below it) holds no live data, so there's nothing to borrow or merge:
+/// its pivot is just dropped outright. This is also what keeps the borrow/
+/// merge paths below safe to assume a leaf sibling — the only way a
+/// `Branch` ever reports `Underflow` is by being degenerate, so by the time
+/// we fall through to them, `pivots[index].child` is guaranteed to be a
+/// leaf, and a leaf's siblings at the same level are always leaves too.
+fn fix_underflow<K, V>(pivots: &mut Vec<Pivot<K, V>>, index: usize, txn: u64) where K: Ord + Clone, V: Clone {
+    if pivots[index].child.is_degenerate() {
+        if pivots.len() > 1 {
+            pivots.remove(index);
+        }
+        // o/w this is the branch's only child: leave the dead pivot for our
+        // own caller's is_degenerate check to find.
+        return;
+    }
+    if index > 0 && leaf_can_lend(&pivots[index - 1]) {
+        borrow_from_left(pivots, index, txn);
+    } else if index + 1 < pivots.len() && leaf_can_lend(&pivots[index + 1]) {
+        borrow_from_right(pivots, index, txn);
+    } else if index > 0 {
+        merge_leaves(pivots, index - 1, index, txn);
+    } else if index + 1 < pivots.len() {
+        merge_leaves(pivots, index, index + 1, txn);
+    }
+    // o/w this is the branch's only child: nothing to borrow from or merge with.
+}
+
+fn leaf_can_lend<K, V>(pivot: &Pivot<K, V>) -> bool {
+    match *pivot.child {
+        Node::Leaf(ref leaf) => leaf.len > min_values_per_leaf,
+        Node::Branch { .. } => false,
+    }
+}
+
+fn as_leaf_mut<K, V>(node: &mut Node<K, V>) -> &mut LeafNode<K, V> {
+    match *node {
+        Node::Leaf(ref mut leaf) => leaf,
+        Node::Branch { .. } => unreachable!("sibling of an underflowing leaf is expected to be a leaf"),
+    }
+}
+
+/// Move the left sibling's largest element in front of `pivots[index]`'s
+/// own elements, and fix up the now-smaller pivot key.
+fn borrow_from_left<K, V>(pivots: &mut Vec<Pivot<K, V>>, index: usize, txn: u64) where K: Ord + Clone, V: Clone {
+    let (left, right) = pivots.split_at_mut(index);
+    let left_leaf = as_leaf_mut(cow_node(&mut left.last_mut().unwrap().child, txn));
+    let cur_leaf = as_leaf_mut(cow_node(&mut right[0].child, txn));
+    let last = left_leaf.len - 1;
+    let moved = unsafe { slice_remove(left_leaf.valid_elements_mut(), last) };
+    left_leaf.len -= 1;
+    unsafe { slice_insert(cur_leaf.with_one_spare_mut(), 0, mem::MaybeUninit::new(moved)) };
+    cur_leaf.len += 1;
+    right[0].min_key = cur_leaf.valid_elements()[0].0.clone();
+}
+
+/// Move the right sibling's smallest element onto the end of
+/// `pivots[index]`'s own elements, and fix up the sibling's pivot key.
+fn borrow_from_right<K, V>(pivots: &mut Vec<Pivot<K, V>>, index: usize, txn: u64) where K: Ord + Clone, V: Clone {
+    let (left, right) = pivots.split_at_mut(index + 1);
+    let cur_leaf = as_leaf_mut(cow_node(&mut left.last_mut().unwrap().child, txn));
+    let right_leaf = as_leaf_mut(cow_node(&mut right[0].child, txn));
+    let moved = unsafe { slice_remove(right_leaf.valid_elements_mut(), 0) };
+    right_leaf.len -= 1;
+    let end = cur_leaf.len;
+    unsafe { slice_insert(cur_leaf.with_one_spare_mut(), end, mem::MaybeUninit::new(moved)) };
+    cur_leaf.len += 1;
+    right[0].min_key = right_leaf.valid_elements()[0].0.clone();
+}
+
+/// Merge `pivots[right_index]`'s elements into `pivots[left_index]`'s leaf
+/// and drop the now-empty right pivot.
+fn merge_leaves<K, V>(pivots: &mut Vec<Pivot<K, V>>, left_index: usize, right_index: usize, txn: u64) where K: Ord + Clone, V: Clone {
+    debug_assert_eq!(right_index, left_index + 1);
+    {
+        let (left, right) = pivots.split_at_mut(right_index);
+        let right_items = as_leaf_mut(cow_node(&mut right[0].child, txn)).take_valid_elements();
+        let left_leaf = as_leaf_mut(cow_node(&mut left[left_index].child, txn));
+        debug_assert!(left_leaf.len + right_items.len() <= max_values_per_leaf,
+                      "both siblings are at or below minimum occupancy, so together they must fit one leaf");
+        // Moved, not cloned, into the surviving leaf: the emptied right leaf
+        // (about to be dropped along with its pivot) never held them twice.
+        for item in right_items {
+            let end = left_leaf.len;
+            unsafe { slice_insert(left_leaf.with_one_spare_mut(), end, mem::MaybeUninit::new(item)) };
+            left_leaf.len += 1;
+        }
+    }
+    pivots.remove(right_index);
+}
+
+// `slice` covers the range *after* the insert (its last slot is spare,
+// as in `with_one_spare_mut`), so only `slice.len() - 1 - idx` elements
+// need to shift right to open up `idx`.
 unsafe fn slice_insert<T>(slice: &mut [T], idx: usize, val: T) {
     ptr::copy(
         slice.as_ptr().offset(idx as isize),
         slice.as_mut_ptr().offset(idx as isize + 1),
-        slice.len() - idx
+        slice.len() - 1 - idx
     );
     ptr::write(slice.get_unchecked_mut(idx), val);
 }
@@ -189,52 +574,373 @@ unsafe fn slice_remove<T>(slice: &mut [T], idx: usize) -> T {
     ret
 }
 
-/// A map based on a BùõÜ-tree
+// `MaybeUninit<T>` is guaranteed to have the same layout as `T`, so a slice
+// known to be fully initialized can be reinterpreted in place instead of
+// copied out. Unsafe: UB if any element isn't actually initialized.
+unsafe fn slice_assume_init_ref<T>(slice: &[mem::MaybeUninit<T>]) -> &[T] {
+    &*(slice as *const [mem::MaybeUninit<T>] as *const [T])
+}
+
+unsafe fn slice_assume_init_mut<T>(slice: &mut [mem::MaybeUninit<T>]) -> &mut [T] {
+    &mut *(slice as *mut [mem::MaybeUninit<T>] as *mut [T])
+}
+
+/// Pack a run of ascending, unique-keyed `(key, value)` pairs into leaves,
+/// one `max_values_per_leaf`-sized chunk at a time, for bulk loading. The
+/// pivots returned form the bottom level for `build_branches` to build on;
+/// an empty `items` yields an empty (but valid) bottom level.
+///
+/// A plain `chunks(max_values_per_leaf)` can leave a trailing chunk below
+/// `min_values_per_leaf` (9 items at a max of 4 chunks to 4, 4, 1), violating
+/// the minimum-occupancy invariant `delete` otherwise maintains; when that
+/// would happen, this borrows back from the chunk before it so both meet the
+/// minimum, the same trade a `delete`-triggered borrow would make. The very
+/// last leaf overall is still allowed below the minimum if `items` itself is
+/// shorter than `min_values_per_leaf`, same as a freshly emptied tree.
+fn build_leaves<K, V>(items: &[(K, V)], txn: u64) -> Vec<Pivot<K, V>>
+    where K: Ord + Clone, V: Clone
+{
+    let mut pivots = Vec::new();
+    let mut start = 0;
+    while start < items.len() {
+        let mut end = ::std::cmp::min(start + max_values_per_leaf, items.len());
+        let remaining_after = items.len() - end;
+        if remaining_after > 0 && remaining_after < min_values_per_leaf {
+            end -= min_values_per_leaf - remaining_after;
+        }
+        let chunk = &items[start..end];
+        let leaf = Arc::new(Node::Leaf(LeafNode::from(chunk, txn)));
+        pivots.push(Pivot { min_key: chunk[0].0.clone(), child: leaf });
+        start = end;
+    }
+    pivots
+}
+
+/// Build the branch levels above a bottom level of pivots (as returned by
+/// `build_leaves`), grouping `max_pivots_per_branch` pivots into each
+/// branch and repeating one level up until a single node remains: the new
+/// root. An empty bottom level yields an empty leaf, same as `BeTree::new`.
+fn build_branches<K, V>(mut level: Vec<Pivot<K, V>>, txn: u64) -> Arc<Node<K, V>>
+    where K: Ord + Clone, V: Clone
+{
+    if level.is_empty() {
+        return Arc::new(Node::Leaf(LeafNode::empty(txn)));
+    }
+    while level.len() > 1 {
+        let mut next = Vec::new();
+        while !level.is_empty() {
+            let take = if level.len() > max_pivots_per_branch { max_pivots_per_branch } else { level.len() };
+            let rest = level.split_off(take);
+            let group = mem::replace(&mut level, rest);
+            let min_key = group[0].min_key.clone();
+            let branch = Arc::new(Node::Branch { pivots: group, buffer: Vec::new(), txn });
+            next.push(Pivot { min_key, child: branch });
+        }
+        level = next;
+    }
+    level.pop().unwrap().child
+}
+
+/// Streaming merge of two ascending, unique-keyed runs into one ascending,
+/// unique-keyed run, for `BeTree::append`. Where both sides have the same
+/// key, `theirs`'s value wins and `mine`'s key is kept, as if its entries
+/// were inserted after everything already in `mine`.
+fn merge_sorted_overwrite<K, V>(mine: Vec<(K, V)>, theirs: Vec<(K, V)>) -> Vec<(K, V)>
+    where K: Ord
+{
+    let mut merged = Vec::with_capacity(mine.len() + theirs.len());
+    let mut mine = mine.into_iter().peekable();
+    let mut theirs = theirs.into_iter().peekable();
+    loop {
+        let take_mine = match (mine.peek(), theirs.peek()) {
+            (Some((mk, _)), Some((tk, _))) => mk < tk,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_mine {
+            merged.push(mine.next().unwrap());
+        } else {
+            // `theirs`'s value wins on a tie; `mine`'s key is kept.
+            let (tk, tv) = theirs.next().unwrap();
+            let same_key = match mine.peek() {
+                Some((mk, _)) => *mk == tk,
+                None => false,
+            };
+            if same_key {
+                let (mk, _) = mine.next().unwrap();
+                merged.push((mk, tv));
+            } else {
+                merged.push((tk, tv));
+            }
+        }
+    }
+    merged
+}
+
+/// A map based on a Bε-tree: writes are buffered at branch nodes and
+/// flushed down to leaves in batches rather than routed straight there, and
+/// a read-only point-in-time view can be taken cheaply via `snapshot`.
 pub struct BeTree< K, V > {
-    root: Node< K, V >
+    root: Arc<Node< K, V >>,
+    // The write transaction in progress. Bumped by `snapshot()` so that
+    // every node reachable at the time of the snapshot is left alone by
+    // later writes: `cow_node` copies rather than mutates any node tagged
+    // with an older transaction than this one.
+    current_txn: u64,
 }
 
-impl<K, V> BeTree<K, V> where K: Copy + Ord, V: Clone {
-    /// Create an empty BùõÜ-tree.
-    pub fn new() -> Self { BeTree { root: Node::Leaf(LeafNode::empty()) } }
+impl<K, V> BeTree<K, V> where K: Ord + Clone, V: Clone {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        BeTree { root: Arc::new(Node::Leaf(LeafNode::empty(0))), current_txn: 0 }
+    }
 
     /// Clear the tree, removing all entries.
     pub fn clear(&mut self) {
-        match self.root {
-            Node::Leaf(ref mut leaf) => {
-                leaf.len = 0
-            },
-            _ => { self.root = Node::Leaf(LeafNode::empty()) }
-        }
+        self.root = Arc::new(Node::Leaf(LeafNode::empty(self.current_txn)));
     }
 
     /// Insert a key-value pair into the tree.
     ///
     /// If the key is already present in the tree, the value is replaced. The key is not updated, though; this matters for
     /// types that can be `==` without being identical.
+    ///
+    /// This is O(1) amortized: the write is only buffered at the root, not
+    /// routed all the way down to a leaf.
     pub fn insert(&mut self, key: K, value: V)
     {
-        self.root.insert(key, value)
+        self.apply(Message::Insert(key, value))
     }
 
     /// Remove a key (and its value) from the tree.
     ///
-    /// If the key is not present, silently does nothing.
+    /// If the key is not present, silently does nothing. Like `insert`, this
+    /// only buffers a message at the root until a flush routes it down.
     pub fn delete(&mut self, key: K)
     {
-        self.root.delete(key)
+        self.apply(Message::Delete(key))
     }
 
     /// Retrieve a reference to the value corresponding to the key.
     pub fn get(&self, key: K) -> Option<&V>
     {
-        self.root.get(key)
+        self.root.get(&key)
+    }
+
+    /// Take a cheap, read-only snapshot of the tree as of this instant.
+    ///
+    /// The snapshot observes `get`/`iter` exactly as the tree stood when
+    /// this was called; later inserts and deletes on `self` never disturb
+    /// it. This is a path copy-on-write scheme rather than a deep copy:
+    /// taking the snapshot is O(1) (an `Arc` clone of the root), and a
+    /// later write only pays for copying the nodes on its own root-to-leaf
+    /// path, the first time each is touched after this call.
+    pub fn snapshot(&mut self) -> Snapshot<K, V> {
+        let snapshot = Snapshot { root: self.root.clone() };
+        // Everything reachable from `snapshot.root` is now effectively
+        // read-only: bumping the txn means the next write to any of it is
+        // seen as "older than current" by `cow_node` and gets copied first.
+        self.current_txn += 1;
+        snapshot
+    }
+
+    fn apply(&mut self, message: Message<K, V>) {
+        let txn = self.current_txn;
+        match cow_node(&mut self.root, txn).push_message(message, txn) {
+            NodeChange::None | NodeChange::Underflow => (),
+            // The root split (or the root leaf overflowed): wrap both
+            // halves in a new, taller root. A bare root is exempt from the
+            // minimum-occupancy invariant, so Underflow is simply ignored.
+            NodeChange::Split(right_pivot) => self.grow_root(right_pivot),
+        }
+    }
+
+    fn grow_root(&mut self, right_pivot: Pivot<K, V>) {
+        let old_root = self.root.clone();
+        let left_pivot = Pivot { min_key: old_root.min_key(), child: old_root };
+        self.root = Arc::new(Node::Branch {
+            pivots: vec![left_pivot, right_pivot],
+            buffer: Vec::new(),
+            txn: self.current_txn,
+        });
+    }
+
+    /// Get the entry for `key`, for a read-modify-write without calling
+    /// `get` and then `insert` separately.
+    ///
+    /// Unlike `BTreeMap`'s entry, this does *not* capture a path to reuse
+    /// for the write: every `insert`/`delete` on this tree is already a
+    /// single O(1) amortized push onto the root's buffer (see
+    /// `push_message`), not a root-to-leaf descent, so there's no second
+    /// descent here for a captured path to save. What `entry` actually
+    /// buys over separate `get`/`insert` calls is doing the one real
+    /// descent - the root-to-leaf walk `get` needs to decide `Occupied` vs
+    /// `Vacant` - exactly once, and presenting it as a single API.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.root.get(&key) {
+            Some(value) => {
+                let value = value.clone();
+                Entry::Occupied(OccupiedEntry { tree: self, key: key, value: value })
+            }
+            None => Entry::Vacant(VacantEntry { tree: self, key: key }),
+        }
+    }
+
+    /// Build a tree from pairs already in ascending, unique-keyed order, in
+    /// one upward pass: leaves are packed near-full and the branch levels
+    /// above them are built level by level, rather than routing each pair
+    /// in from the root as `insert` does. Roughly linear in the number of
+    /// pairs, unlike inserting them one at a time.
+    ///
+    /// `iter` must already be sorted ascending by key with no duplicate
+    /// keys; use `FromIterator` instead if it isn't.
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let items: Vec<(K, V)> = iter.into_iter().collect();
+        debug_assert!(items.windows(2).all(|w| w[0].0 < w[1].0),
+                      "from_sorted_iter requires ascending, unique-keyed input");
+        let leaves = build_leaves(&items, 0);
+        BeTree { root: build_branches(leaves, 0), current_txn: 0 }
+    }
+
+    /// Merge `other`'s entries into this tree by streaming both in
+    /// ascending key order and re-packing, rather than inserting `other`'s
+    /// entries one at a time: roughly linear rather than N log N. Where
+    /// both trees have a value for a key, `other`'s value wins, as if its
+    /// entries had been inserted after everything already in `self`.
+    pub fn append(&mut self, other: Self) {
+        let mut mine = Vec::new();
+        self.root.collect_into(&mut mine);
+        mine.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut theirs = Vec::new();
+        other.root.collect_into(&mut theirs);
+        theirs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let merged = merge_sorted_overwrite(mine, theirs);
+        let leaves = build_leaves(&merged, self.current_txn);
+        self.root = build_branches(leaves, self.current_txn);
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for BeTree<K, V> where K: Ord + Clone, V: Clone {
+    /// Build a tree from pairs in any order, sorting (and resolving
+    /// duplicate keys in favor of the later pair, as repeated `insert`s of
+    /// the same key would) before bulk loading via `from_sorted_iter`.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut items: Vec<(K, V)> = iter.into_iter().collect();
+        // Stable, so equal keys keep their original relative order here...
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        // ...which lets reversing, then keeping the first of each run (the
+        // last one inserted), then reversing back, dedup to the later pair.
+        items.reverse();
+        items.dedup_by(|a, b| a.0 == b.0);
+        items.reverse();
+        BeTree::from_sorted_iter(items)
+    }
+}
+
+/// A read-only, point-in-time view of a `BeTree`, obtained from
+/// `BeTree::snapshot`. Reads are unaffected by writes made to the tree
+/// after the snapshot was taken; the nodes it still references are kept
+/// alive (and reclaimed once the last snapshot or tree referencing them is
+/// dropped) by ordinary `Arc` reference counting.
+pub struct Snapshot<K, V> {
+    root: Arc<Node<K, V>>,
+}
+
+impl<K, V> Snapshot<K, V> where K: Ord + Clone, V: Clone {
+    /// Retrieve a reference to the value corresponding to the key, as of
+    /// the instant this snapshot was taken.
+    pub fn get(&self, key: K) -> Option<&V> {
+        self.root.get(&key)
+    }
+
+    /// All entries as of the instant this snapshot was taken, in ascending
+    /// key order.
+    pub fn iter(&self) -> ::std::vec::IntoIter<(K, V)> {
+        let mut entries = Vec::new();
+        self.root.collect_into(&mut entries);
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.into_iter()
+    }
+}
+
+/// A view into a single entry in a `BeTree`, obtained from `BeTree::entry`.
+///
+/// Unlike `BTreeMap`'s entry, `or_insert`/`or_insert_with` hand back an
+/// owned `V` rather than `&mut V`: the value an `Occupied` entry was built
+/// from may have come from a buffered message rather than a leaf, so there's
+/// no single slot in the tree to lend a live reference into. And unlike
+/// `BTreeMap`'s entry, there's no captured path to a leaf slot reused by the
+/// write: writes to this tree are already O(1) amortized root-buffer pushes
+/// (see `BeTree::entry`'s doc comment), so there's no second descent for a
+/// captured path to avoid in the first place.
+pub enum Entry<'a, K: 'a, V: 'a> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+/// An `Entry` for a key with a value already in the tree.
+pub struct OccupiedEntry<'a, K: 'a, V: 'a> {
+    tree: &'a mut BeTree<K, V>,
+    key: K,
+    value: V,
+}
+
+/// An `Entry` for a key with no value in the tree.
+pub struct VacantEntry<'a, K: 'a, V: 'a> {
+    tree: &'a mut BeTree<K, V>,
+    key: K,
+}
+
+impl<'a, K, V> Entry<'a, K, V> where K: Ord + Clone, V: Clone {
+    /// Ensure the entry has a value, inserting `default` if it was vacant,
+    /// and return the value either way.
+    pub fn or_insert(self, default: V) -> V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Like `or_insert`, but only computes the default value if the entry
+    /// was vacant.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> V {
+        match self {
+            Entry::Occupied(entry) => entry.value,
+            Entry::Vacant(entry) => {
+                let value = default();
+                entry.tree.insert(entry.key, value.clone());
+                value
+            }
+        }
+    }
+
+    /// If the entry is occupied, apply `f` to its value and write the
+    /// result back as a single buffered upsert. A vacant entry passes
+    /// through untouched, ready for a following `or_insert`/`or_insert_with`.
+    ///
+    /// The write-back always happens, even if `f` leaves the value
+    /// unchanged: there's no cheap way to tell whether it did, and the
+    /// entry's original descent already happened, so the write goes through
+    /// the ordinary buffered-insert path rather than mutating a slot in
+    /// place. `entry(k).and_modify(f).or_insert(v)` is not cheaper than a
+    /// plain `get`-then-`insert` would have been for the occupied case.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(&mut entry.value);
+                entry.tree.insert(entry.key.clone(), entry.value.clone());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use BeTree;
+    use Node;
+    use build_leaves;
 
     #[test]
     fn can_construct() {
@@ -306,9 +1012,295 @@ mod tests {
         assert_eq!(Some(&'y'), b.get(2));
     }
 
+    #[test]
+    fn delete_to_empty_then_delete_again_does_not_panic() {
+        // Drives a leaf to empty while it's the sole child of its branch
+        // (nothing for fix_underflow to borrow from or merge with), then
+        // touches that dead pivot again: used to panic in `min_key` because
+        // the branch never collapsed the empty leaf away.
+        let mut b = BeTree::new();
+        for i in 0..7 { b.insert(i, i); }
+        for i in 0..7 { b.delete(i); }
+        b.delete(0);
+        for i in 0..7 { assert_eq!(None, b.get(i)); }
+    }
+
     #[test]
     fn can_delete_nothing() {
         let mut b = BeTree::<i32, char>::new();
         b.delete(0);
     }
+
+    #[test]
+    fn get_sees_buffered_insert_before_flush() {
+        let mut b = BeTree::new();
+        for i in 0..::max_values_per_leaf {
+            b.insert(i, i);
+        }
+        // splits the root into a branch with an empty buffer
+        b.insert(::max_values_per_leaf, ::max_values_per_leaf);
+        // buffered at the root, well under max_buffer_size, so not yet flushed
+        b.insert(::max_values_per_leaf + 1, ::max_values_per_leaf + 1);
+        assert_eq!(Some(&(::max_values_per_leaf + 1)), b.get(::max_values_per_leaf + 1));
+    }
+
+    #[test]
+    fn buffered_delete_masks_leaf_value() {
+        let mut b = BeTree::new();
+        for i in 0..::max_values_per_leaf {
+            b.insert(i, i);
+        }
+        // splits the root into a branch; key 1 now lives in a leaf child
+        b.insert(::max_values_per_leaf, ::max_values_per_leaf);
+        // buffered at the root, shadowing the leaf's own copy of the key
+        b.delete(1);
+        assert_eq!(None, b.get(1));
+    }
+
+    #[test]
+    fn delete_merges_underflowed_leaf_into_sibling() {
+        let mut b = BeTree::new();
+        let n = ::max_pivots_per_branch * ::max_values_per_leaf * 2;
+        for i in 0..n { b.insert(i, i); }
+        for i in 0..n { assert_eq!(Some(&i), b.get(i)); }
+        // delete all but one key, driving leaves below the minimum
+        // occupancy invariant and forcing borrows/merges all the way up
+        for i in 1..n { b.delete(i); }
+        assert_eq!(Some(&0), b.get(0));
+        for i in 1..n { assert_eq!(None, b.get(i)); }
+    }
+
+    #[test]
+    fn fuzz_insert_delete_against_btreemap_oracle_does_not_panic() {
+        // A cheap deterministic LCG stands in for a real PRNG crate, which
+        // this edition-2015, no-Cargo.toml tree has no way to depend on.
+        let mut state: u64 = 0xC0FFEE;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+
+        let mut oracle = ::std::collections::BTreeMap::new();
+        let mut tree = BeTree::new();
+        let key_space = 40;
+        // Drives plenty of underflows/merges (and the branch-collapse path
+        // they can trigger) over a small shared key space, well past the
+        // ~40 operations that used to be enough to panic.
+        for _ in 0..2000 {
+            let key = next() % key_space;
+            if next() % 2 == 0 {
+                oracle.insert(key, key);
+                tree.insert(key, key);
+            } else {
+                oracle.remove(&key);
+                tree.delete(key);
+            }
+            assert_eq!(oracle.get(&key), tree.get(key));
+        }
+        for key in 0..key_space {
+            assert_eq!(oracle.get(&key), tree.get(key));
+        }
+    }
+
+    #[test]
+    fn branch_splits_when_pivots_exceed_max() {
+        let mut b = BeTree::new();
+        // enough leaves to force the root branch itself to split and grow
+        // a new root, not just the leaves underneath it
+        let n = (::max_pivots_per_branch + 2) * ::max_values_per_leaf;
+        for i in 0..n { b.insert(i, i); }
+        for i in 0..n { assert_eq!(Some(&i), b.get(i)); }
+    }
+
+    #[test]
+    fn snapshot_is_unaffected_by_later_writes() {
+        let mut b = BeTree::new();
+        let n = ::max_pivots_per_branch * ::max_values_per_leaf * 2;
+        for i in 0..n { b.insert(i, i); }
+
+        let snap = b.snapshot();
+
+        // mutate the live tree every way we can: overwrite, delete, insert
+        for i in 0..n { b.insert(i, i + 1000); }
+        b.delete(0);
+        b.insert(n, n);
+
+        for i in 0..n { assert_eq!(Some(&i), snap.get(i)); }
+        assert_eq!(None, snap.get(n));
+
+        assert_eq!(None, b.get(0));
+        for i in 1..n { assert_eq!(Some(&(i + 1000)), b.get(i)); }
+        assert_eq!(Some(&n), b.get(n));
+    }
+
+    #[test]
+    fn snapshot_iter_returns_sorted_entries_as_of_the_snapshot() {
+        let mut b = BeTree::new();
+        let n = ::max_pivots_per_branch * ::max_values_per_leaf;
+        for i in (0..n).rev() { b.insert(i, i * 2); }
+
+        let snap = b.snapshot();
+        b.insert(n, n * 2);
+
+        let got: Vec<(usize, usize)> = snap.iter().collect();
+        let expected: Vec<(usize, usize)> = (0..n).map(|i| (i, i * 2)).collect();
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn entry_or_insert_on_vacant_inserts_default() {
+        let mut b = BeTree::<i32, i32>::new();
+        let v = b.entry(0).or_insert(5);
+        assert_eq!(5, v);
+        assert_eq!(Some(&5), b.get(0));
+    }
+
+    #[test]
+    fn entry_or_insert_on_occupied_keeps_existing() {
+        let mut b = BeTree::new();
+        b.insert(0, 1);
+        let v = b.entry(0).or_insert(5);
+        assert_eq!(1, v);
+        assert_eq!(Some(&1), b.get(0));
+    }
+
+    #[test]
+    fn entry_or_insert_with_only_calls_default_when_vacant() {
+        let mut b = BeTree::new();
+        b.insert(0, 1);
+        b.entry(0).or_insert_with(|| panic!("default should not run for an occupied entry"));
+        assert_eq!(Some(&1), b.get(0));
+    }
+
+    #[test]
+    fn entry_and_modify_mutates_existing_value() {
+        let mut b = BeTree::new();
+        b.insert(0, 1);
+        b.entry(0).and_modify(|v| *v += 10);
+        assert_eq!(Some(&11), b.get(0));
+    }
+
+    #[test]
+    fn entry_and_modify_does_nothing_on_vacant() {
+        let mut b = BeTree::<i32, i32>::new();
+        b.entry(0).and_modify(|v| *v += 10).or_insert(1);
+        assert_eq!(Some(&1), b.get(0));
+    }
+
+    #[test]
+    fn from_sorted_iter_contains_every_pair() {
+        let n = (::max_pivots_per_branch + 2) * ::max_values_per_leaf;
+        let b = BeTree::from_sorted_iter((0..n).map(|i| (i, i * 2)));
+        for i in 0..n {
+            assert_eq!(Some(&(i * 2)), b.get(i));
+        }
+    }
+
+    #[test]
+    fn build_leaves_keeps_every_leaf_at_or_above_the_minimum() {
+        // max_values_per_leaf + 1 items: a naive chunks(max) split would
+        // leave a trailing leaf of just 1 element, below min_values_per_leaf.
+        let n = ::max_values_per_leaf + 1;
+        let items: Vec<(usize, usize)> = (0..n).map(|i| (i, i)).collect();
+        let pivots = build_leaves(&items, 0);
+        assert!(pivots.len() >= 2);
+        for pivot in &pivots {
+            match *pivot.child {
+                Node::Leaf(ref leaf) => assert!(leaf.len >= ::min_values_per_leaf),
+                Node::Branch { .. } => panic!("build_leaves should only produce leaves"),
+            }
+        }
+    }
+
+    #[test]
+    fn from_iter_sorts_unsorted_input_and_keeps_the_later_duplicate() {
+        let pairs = vec![(3, 'c'), (1, 'a'), (2, 'b'), (1, 'z')];
+        let b: BeTree<i32, char> = pairs.into_iter().collect();
+        assert_eq!(Some(&'z'), b.get(1));
+        assert_eq!(Some(&'b'), b.get(2));
+        assert_eq!(Some(&'c'), b.get(3));
+    }
+
+    #[test]
+    fn append_merges_both_trees_with_other_winning_ties() {
+        let n = ::max_pivots_per_branch * ::max_values_per_leaf;
+        let mut a = BeTree::new();
+        for i in 0..n { a.insert(i, i); }
+
+        let mut b = BeTree::new();
+        for i in n / 2..n + n / 2 { b.insert(i, i + 1000); }
+
+        a.append(b);
+
+        for i in 0..n / 2 { assert_eq!(Some(&i), a.get(i)); }
+        for i in n / 2..n + n / 2 { assert_eq!(Some(&(i + 1000)), a.get(i)); }
+    }
+
+    use std::rc::Rc;
+    use std::cell::Cell;
+
+    /// A value whose `Drop` bumps a shared counter, so tests can assert that
+    /// every value stored in the tree is dropped exactly once: neither
+    /// leaked (a stale `MaybeUninit` slot never dropped) nor double-dropped
+    /// (an overwritten slot dropped twice).
+    #[derive(Clone)]
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl DropCounter {
+        fn new(counter: &Rc<Cell<usize>>) -> Self {
+            DropCounter(counter.clone())
+        }
+    }
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn overwriting_a_key_drops_the_old_value_exactly_once() {
+        let counter = Rc::new(Cell::new(0));
+        let mut b = BeTree::new();
+        b.insert(0, DropCounter::new(&counter));
+        assert_eq!(0, counter.get());
+        b.insert(0, DropCounter::new(&counter));
+        assert_eq!(1, counter.get(), "the replaced value should have dropped");
+        b.clear();
+        assert_eq!(2, counter.get(), "the surviving value should have dropped on clear");
+    }
+
+    #[test]
+    fn split_and_delete_drop_every_value_exactly_once() {
+        let counter = Rc::new(Cell::new(0));
+        let n = ::max_pivots_per_branch * ::max_values_per_leaf;
+        {
+            let mut b = BeTree::new();
+            for i in 0..n {
+                b.insert(i, DropCounter::new(&counter));
+            }
+            assert_eq!(0, counter.get());
+            for i in 0..n / 2 {
+                b.delete(i);
+            }
+            // Deletes are only buffered messages until a flush routes them
+            // down to a leaf, so the values they remove aren't necessarily
+            // dropped yet; only dropping the whole tree is guaranteed to
+            // drop everything still live in it.
+        }
+        assert_eq!(n, counter.get(), "every inserted value should drop exactly once");
+    }
+
+    #[test]
+    fn string_keys_work_without_copy() {
+        let mut b = BeTree::new();
+        b.insert("banana".to_string(), 2);
+        b.insert("apple".to_string(), 1);
+        b.insert("cherry".to_string(), 3);
+        assert_eq!(Some(&1), b.get("apple".to_string()));
+        assert_eq!(Some(&2), b.get("banana".to_string()));
+        assert_eq!(Some(&3), b.get("cherry".to_string()));
+        b.delete("banana".to_string());
+        assert_eq!(None, b.get("banana".to_string()));
+    }
 }